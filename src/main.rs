@@ -1,14 +1,18 @@
+mod decoder;
+
+use decoder::{open_animation, Frame, FramePixels};
 use framebuffer::{Framebuffer, FramebufferError, KdMode};
-use gif::{ColorOutput, Decoder, DecodingError};
+use gif::{DisposalMethod, Repeat};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
 use pico_args::Arguments;
 use std::error::Error;
-use std::fs::File;
 use std::process;
 use std::thread;
 use std::time::{Duration, Instant};
 
 const HELP: &str = "\
-Framebuffer GIF animation player
+Framebuffer animation player
 
 USAGE:
   fba [OPTIONS] --number NUMBER [INPUT]
@@ -18,12 +22,14 @@ FLAGS:
 
 OPTIONS:
   -d, --device DEVICE       Framebuffer device file [default: /dev/fb0]
-  -i, --interval NUMBER     Interval step for displaying GIF frames (milliseconds) [default: 5]
+  -i, --interval NUMBER     Interval step for displaying animation frames (milliseconds) [default: 5]
   -o, --once                Play the file just one time
-  -c, --center              Center the GIF
+  -l, --loop N|infinite     Override the animation's loop count [default: the file's own loop count]
+  -c, --center              Center the animation
+  -s, --scale MODE          Fit the animation to the screen: none, fit, fill, stretch [default: none]
 
 ARGS:
-  <FILE>                    GIF file to be played
+  <FILE>                    Animation file to be played (.gif, .png/APNG, .webp)
 ";
 
 /// Command line arguments
@@ -32,8 +38,10 @@ struct Args {
     device: String,
     interval: u64,
     once: bool,
+    loop_count: Option<Repeat>,
     center: bool,
-    gif_file: String,
+    scale_mode: ScaleMode,
+    input_file: String,
 }
 
 /// Information about the framebuffer
@@ -42,6 +50,7 @@ struct FramebufferInfo {
     height: isize,
     channels: isize,
     alignment: isize,
+    pack_pixel: Box<dyn Fn(u8, u8, u8) -> u32>,
 }
 
 struct Offset {
@@ -49,6 +58,77 @@ struct Offset {
     y: isize,
 }
 
+/// How a decoded animation should be resampled to fit the framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScaleMode {
+    /// Play the animation at its native size.
+    None,
+    /// Resample to fit within the screen, preserving aspect ratio.
+    Fit,
+    /// Resample to cover the screen, preserving aspect ratio and cropping overflow.
+    Fill,
+    /// Resample to exactly the screen size, ignoring aspect ratio.
+    Stretch,
+}
+
+/// The per-axis resize factor chosen for a `ScaleMode`, computed once from the animation's
+/// native dimensions and the framebuffer's, then reused for every frame.
+struct Scale {
+    mode: ScaleMode,
+    x_factor: f64,
+    y_factor: f64,
+    target_width: isize,
+    target_height: isize,
+}
+
+/// An axis-aligned rectangle of framebuffer pixels.
+#[derive(Clone, Copy)]
+struct Rect {
+    left: isize,
+    top: isize,
+    width: isize,
+    height: isize,
+}
+
+impl Rect {
+    /// Clips the rectangle to the framebuffer's bounds, or `None` if it falls entirely outside.
+    fn clip(self, fb_info: &FramebufferInfo) -> Option<Rect> {
+        let left = self.left.max(0);
+        let top = self.top.max(0);
+        let right = (self.left + self.width).min(fb_info.width);
+        let bottom = (self.top + self.height).min(fb_info.height);
+        if left >= right || top >= bottom {
+            return None;
+        }
+        Some(Rect { left, top, width: right - left, height: bottom - top })
+    }
+
+    /// The smallest rectangle containing both `self` and `other`.
+    fn union(self, other: Rect) -> Rect {
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        let right = (self.left + self.width).max(other.left + other.width);
+        let bottom = (self.top + self.height).max(other.top + other.height);
+        Rect { left, top, width: right - left, height: bottom - top }
+    }
+}
+
+/// Unions two optional rectangles, treating `None` as "nothing here".
+fn union_rects(a: Option<Rect>, b: Option<Rect>) -> Option<Rect> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.union(b)),
+        (Some(rect), None) | (None, Some(rect)) => Some(rect),
+        (None, None) => None,
+    }
+}
+
+/// The disposal a frame requires once the next frame is about to be drawn, together with the
+/// framebuffer rectangle (already offset) that disposal applies to.
+struct PendingDisposal {
+    method: DisposalMethod,
+    rect: Rect,
+}
+
 /// Parses command line arguments
 fn parse_args() -> Result<Args, pico_args::Error> {
     let mut pargs = Arguments::from_env();
@@ -63,8 +143,10 @@ fn parse_args() -> Result<Args, pico_args::Error> {
         device: pargs.opt_value_from_str(["-d", "--device"])?.unwrap_or("/dev/fb0".to_string()),
         interval: pargs.opt_value_from_fn(["-i", "--interval"], parse_interval)?.unwrap_or(5),
         once: pargs.contains(["-o", "--once"]),
+        loop_count: pargs.opt_value_from_fn(["-l", "--loop"], parse_loop)?,
         center: pargs.contains(["-c", "--center"]),
-        gif_file: pargs.free_from_str()?,
+        scale_mode: pargs.opt_value_from_fn(["-s", "--scale"], parse_scale_mode)?.unwrap_or(ScaleMode::None),
+        input_file: pargs.free_from_str()?,
     };
 
     // It's up to the caller what to do with the remaining arguments.
@@ -81,13 +163,124 @@ fn parse_interval(s: &str) -> Result<u64, &'static str> {
     s.parse().map_err(|_| "not a number")
 }
 
+/// Parses the `--loop` argument, either a finite iteration count or the literal `infinite`.
+fn parse_loop(s: &str) -> Result<Repeat, &'static str> {
+    if s.eq_ignore_ascii_case("infinite") {
+        return Ok(Repeat::Infinite);
+    }
+    s.parse().map(Repeat::Finite).map_err(|_| "not a number or 'infinite'")
+}
+
+/// Parses the `--scale` argument.
+fn parse_scale_mode(s: &str) -> Result<ScaleMode, &'static str> {
+    match s {
+        "none" => Ok(ScaleMode::None),
+        "fit" => Ok(ScaleMode::Fit),
+        "fill" => Ok(ScaleMode::Fill),
+        "stretch" => Ok(ScaleMode::Stretch),
+        _ => Err("expected one of: none, fit, fill, stretch"),
+    }
+}
+
+/// Computes the per-axis resize factor for `mode`, given the animation's native dimensions and
+/// the framebuffer's.
+fn compute_scale(mode: ScaleMode, source_width: isize, source_height: isize, fb_width: isize, fb_height: isize) -> Scale {
+    let (x_factor, y_factor) = match mode {
+        ScaleMode::None => (1.0, 1.0),
+        ScaleMode::Stretch => (fb_width as f64 / source_width as f64, fb_height as f64 / source_height as f64),
+        ScaleMode::Fit => {
+            let factor = (fb_width as f64 / source_width as f64).min(fb_height as f64 / source_height as f64);
+            (factor, factor)
+        }
+        ScaleMode::Fill => {
+            let factor = (fb_width as f64 / source_width as f64).max(fb_height as f64 / source_height as f64);
+            (factor, factor)
+        }
+    };
+
+    let target_width = (source_width as f64 * x_factor).round() as isize;
+    let target_height = (source_height as f64 * y_factor).round() as isize;
+    Scale { mode, x_factor, y_factor, target_width, target_height }
+}
+
+/// Expands a decoded frame's pixels into an RGBA image, resolving palette indices and
+/// transparency for `FramePixels::Indexed`.
+fn to_rgba_image(frame: &Frame) -> RgbaImage {
+    let rgba = match &frame.pixels {
+        FramePixels::Rgba(buffer) => buffer.clone(),
+        FramePixels::Indexed { buffer, palette, transparent } => {
+            let mut rgba = Vec::with_capacity(buffer.len() * 4);
+            for &index in buffer {
+                let j = index as usize * 3;
+                let alpha = if *transparent == Some(index) { 0 } else { 255 };
+                rgba.extend_from_slice(&[palette[j], palette[j + 1], palette[j + 2], alpha]);
+            }
+            rgba
+        }
+    };
+    RgbaImage::from_raw(frame.width as u32, frame.height as u32, rgba).expect("frame buffer size matches its declared dimensions")
+}
+
+/// Resamples a decoded frame to the target `Scale`, repositioning it to match.
+fn scale_frame(frame: Frame, scale: &Scale) -> Frame {
+    if scale.mode == ScaleMode::None {
+        return frame;
+    }
+
+    let width = ((frame.width as f64 * scale.x_factor).round() as u32).max(1);
+    let height = ((frame.height as f64 * scale.y_factor).round() as u32).max(1);
+    let resized = imageops::resize(&to_rgba_image(&frame), width, height, FilterType::Lanczos3);
+
+    Frame {
+        pixels: FramePixels::Rgba(resized.into_raw()),
+        left: (frame.left as f64 * scale.x_factor).round() as isize,
+        top: (frame.top as f64 * scale.y_factor).round() as isize,
+        width: width as isize,
+        height: height as isize,
+        delay: frame.delay,
+        dispose: frame.dispose,
+    }
+}
+
 /// Retrieves information about the framebuffer.
 fn get_framebuffer_info(fb: &Framebuffer) -> FramebufferInfo {
     let width = fb.var_screen_info.xres as isize;
     let height = fb.var_screen_info.yres as isize;
     let channels = fb.var_screen_info.bits_per_pixel as isize / 8;
     let alignment = fb.fix_screen_info.line_length as isize - fb.var_screen_info.xres as isize * channels;
-    FramebufferInfo { width, height, channels, alignment }
+    let pack_pixel = build_pixel_packer(fb);
+    FramebufferInfo { width, height, channels, alignment, pack_pixel }
+}
+
+/// Packs one 8-bit color channel value into the bit field described by `offset`/`length`,
+/// scaling it down to the field's bit depth.
+fn pack_channel(value: u8, offset: u32, length: u32) -> u32 {
+    if length == 0 {
+        return 0;
+    }
+    let scaled = if length >= 8 { value as u32 } else { (value >> (8 - length)) as u32 };
+    scaled << offset
+}
+
+/// Builds a closure that packs an 8-bit RGB triple into the framebuffer's native pixel format,
+/// honoring its red/green/blue bitfield offsets and lengths. This lets the player target
+/// non-BGR panels (e.g. RGB565, XRGB8888) instead of assuming 24/32-bit BGR. Every pixel the
+/// player draws is fully opaque, so if the format also carries a transp field (e.g. ARGB8888),
+/// that field is packed fully on rather than left at 0 — on panels/compositors that honor it,
+/// a 0 there would make everything the player draws invisible.
+fn build_pixel_packer(fb: &Framebuffer) -> Box<dyn Fn(u8, u8, u8) -> u32> {
+    let (red_offset, red_length) = (fb.var_screen_info.red.offset, fb.var_screen_info.red.length);
+    let (green_offset, green_length) = (fb.var_screen_info.green.offset, fb.var_screen_info.green.length);
+    let (blue_offset, blue_length) = (fb.var_screen_info.blue.offset, fb.var_screen_info.blue.length);
+    let (transp_offset, transp_length) = (fb.var_screen_info.transp.offset, fb.var_screen_info.transp.length);
+    let opaque = pack_channel(0xff, transp_offset, transp_length);
+
+    Box::new(move |r, g, b| {
+        opaque
+            | pack_channel(r, red_offset, red_length)
+            | pack_channel(g, green_offset, green_length)
+            | pack_channel(b, blue_offset, blue_length)
+    })
 }
 
 /// Sets the keyboard display mode to either graphics or text mode.
@@ -105,8 +298,8 @@ fn set_keyboard_display_mode(kd_mode: KdMode) -> Result<i32, FramebufferError> {
     Framebuffer::set_kd_mode(kd_mode)
 }
 
-/// Delays the execution of the next frame in a GIF animation based on the specified delay and
-/// the elapsed time since the previous frame was prepared.
+/// Delays the execution of the next frame in an animation based on the specified delay and the
+/// elapsed time since the previous frame was prepared.
 fn postpone_next_frame(delay: u64, elapsed: &Duration) {
     let elapsed_time = elapsed.as_millis() as u64;
     if elapsed_time < delay {
@@ -115,50 +308,154 @@ fn postpone_next_frame(delay: u64, elapsed: &Duration) {
     }
 }
 
-/// Creates a `gif::Decoder` instance to decode a GIF file.
-fn get_gif_decoder(gif_file: &str) -> Result<Decoder<File>, DecodingError> {
-    let file = File::open(gif_file)?;
-    let mut decode_options = gif::DecodeOptions::new();
-    decode_options.set_color_output(ColorOutput::Indexed);
-    decode_options.read_info(file)
+/// Writes a single packed RGB color into `fb_frame` at pixel `(x, y)`.
+fn write_pixel(fb_frame: &mut [u8], fb_info: &FramebufferInfo, x: isize, y: isize, r: u8, g: u8, b: u8) {
+    let i = fb_index(fb_info, x, y);
+    let packed = (fb_info.pack_pixel)(r, g, b);
+    let bytes = packed.to_le_bytes();
+    let channels = fb_info.channels as usize;
+    fb_frame[i..i + channels].copy_from_slice(&bytes[..channels]);
 }
 
-/// Processes a single frame of a GIF image and updates the framebuffer frame buffer accordingly.
-fn process_gif_frame(gif_frame: &gif::Frame, gif_palette: &[u8], fb_frame: &mut [u8], fb_info: &FramebufferInfo, offset: &Offset) {
-    let buffer = &gif_frame.buffer;
-    let lines = buffer.chunks(gif_frame.width as usize);
-
-    for (y, line) in lines.enumerate() {
-        let y = y as isize + offset.y + gif_frame.top as isize;
-        if y < 0 {
-            continue;
-        }
+/// Processes a single decoded frame, updates the framebuffer frame buffer accordingly, and
+/// returns the bounding box of the pixels it actually touched (`None` if none were).
+fn process_frame(frame: &Frame, fb_frame: &mut [u8], fb_info: &FramebufferInfo, offset: &Offset) -> Option<Rect> {
+    let mut bounds: Option<(isize, isize, isize, isize)> = None;
+    let mut touch = |x: isize, y: isize| {
+        bounds = Some(match bounds {
+            None => (x, y, x, y),
+            Some((min_x, min_y, max_x, max_y)) => (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)),
+        });
+    };
 
-        if y >= fb_info.height {
-            break;
-        }
+    match &frame.pixels {
+        FramePixels::Indexed { buffer, palette, transparent } => {
+            let lines = buffer.chunks(frame.width as usize);
+            for (y, line) in lines.enumerate() {
+                let y = y as isize + offset.y + frame.top;
+                if y < 0 {
+                    continue;
+                }
+                if y >= fb_info.height {
+                    break;
+                }
 
-        for (x, pixel) in line.iter().enumerate() {
-            let x = x as isize + offset.x + gif_frame.left as isize;
-            if x < 0 {
-                continue;
-            }
-            if x >= fb_info.width {
-                break;
+                for (x, pixel) in line.iter().enumerate() {
+                    let x = x as isize + offset.x + frame.left;
+                    if x < 0 {
+                        continue;
+                    }
+                    if x >= fb_info.width {
+                        break;
+                    }
+
+                    if *transparent == Some(*pixel) {
+                        continue;
+                    }
+
+                    let j = *pixel as usize * 3;
+                    write_pixel(fb_frame, fb_info, x, y, palette[j], palette[j + 1], palette[j + 2]);
+                    touch(x, y);
+                }
             }
-
-            if let Some(transparent) = gif_frame.transparent {
-                if *pixel == transparent {
+        }
+        FramePixels::Rgba(buffer) => {
+            let lines = buffer.chunks(frame.width as usize * 4);
+            for (y, line) in lines.enumerate() {
+                let y = y as isize + offset.y + frame.top;
+                if y < 0 {
                     continue;
                 }
+                if y >= fb_info.height {
+                    break;
+                }
+
+                for (x, pixel) in line.chunks(4).enumerate() {
+                    let x = x as isize + offset.x + frame.left;
+                    if x < 0 {
+                        continue;
+                    }
+                    if x >= fb_info.width {
+                        break;
+                    }
+
+                    // Key on alpha rather than blending with the destination: the framebuffer
+                    // has no alpha channel to read back and composite against.
+                    if pixel[3] < 128 {
+                        continue;
+                    }
+
+                    write_pixel(fb_frame, fb_info, x, y, pixel[0], pixel[1], pixel[2]);
+                    touch(x, y);
+                }
             }
+        }
+    }
+
+    bounds.map(|(min_x, min_y, max_x, max_y)| Rect { left: min_x, top: min_y, width: max_x - min_x + 1, height: max_y - min_y + 1 })
+}
+
+/// Computes the framebuffer byte offset of pixel `(x, y)`.
+fn fb_index(fb_info: &FramebufferInfo, x: isize, y: isize) -> usize {
+    ((y * fb_info.width + x) * fb_info.channels + y * fb_info.alignment) as usize
+}
+
+/// Writes only the scanlines covered by `rect` from `fb_frame` into the real framebuffer,
+/// instead of pushing the whole buffer on every frame.
+fn write_dirty_rect(fb: &mut Framebuffer, fb_frame: &[u8], fb_info: &FramebufferInfo, rect: Rect) {
+    let Some(rect) = rect.clip(fb_info) else {
+        return;
+    };
+    let row_bytes = rect.width as usize * fb_info.channels as usize;
+    for y in rect.top..rect.top + rect.height {
+        let i = fb_index(fb_info, rect.left, y);
+        fb.frame[i..i + row_bytes].copy_from_slice(&fb_frame[i..i + row_bytes]);
+    }
+}
+
+/// Copies the pixels of the given (already clipped) rectangle out of `fb_frame`, so they can
+/// later be restored by a `Previous` disposal.
+fn snapshot_rect(fb_frame: &[u8], fb_info: &FramebufferInfo, rect: Rect) -> Vec<u8> {
+    let mut snapshot = Vec::new();
+    for y in rect.top..rect.top + rect.height {
+        let i = fb_index(fb_info, rect.left, y);
+        snapshot.extend_from_slice(&fb_frame[i..i + rect.width as usize * fb_info.channels as usize]);
+    }
+    snapshot
+}
+
+/// Writes a previously captured `snapshot_rect` back into `fb_frame`.
+fn restore_rect(fb_frame: &mut [u8], fb_info: &FramebufferInfo, rect: Rect, snapshot: &[u8]) {
+    let row_bytes = rect.width as usize * fb_info.channels as usize;
+    for (row, y) in (rect.top..rect.top + rect.height).enumerate() {
+        let i = fb_index(fb_info, rect.left, y);
+        fb_frame[i..i + row_bytes].copy_from_slice(&snapshot[row * row_bytes..(row + 1) * row_bytes]);
+    }
+}
 
-            let i = ((y * fb_info.width + x) * fb_info.channels + y * fb_info.alignment) as usize;
-            let j = *pixel as usize * 3;
+/// Clears a rectangle of `fb_frame` back to the background color before the next frame is drawn.
+fn clear_rect(fb_frame: &mut [u8], fb_info: &FramebufferInfo, rect: Rect) {
+    let row_bytes = rect.width as usize * fb_info.channels as usize;
+    for y in rect.top..rect.top + rect.height {
+        let i = fb_index(fb_info, rect.left, y);
+        fb_frame[i..i + row_bytes].fill(0);
+    }
+}
 
-            fb_frame[i] = gif_palette[j + 2];
-            fb_frame[i + 1] = gif_palette[j + 1];
-            fb_frame[i + 2] = gif_palette[j];
+/// Applies a previous frame's disposal method to `fb_frame` before the next frame is painted,
+/// returning the (clipped) rectangle it actually touched.
+fn dispose_frame(disposal: PendingDisposal, previous_snapshot: &mut Option<Vec<u8>>, fb_frame: &mut [u8], fb_info: &FramebufferInfo) -> Option<Rect> {
+    let rect = disposal.rect.clip(fb_info)?;
+    match disposal.method {
+        DisposalMethod::Any | DisposalMethod::Keep => None,
+        DisposalMethod::Background => {
+            clear_rect(fb_frame, fb_info, rect);
+            Some(rect)
+        }
+        DisposalMethod::Previous => {
+            let snapshot = previous_snapshot.take()?;
+            restore_rect(fb_frame, fb_info, rect, &snapshot);
+            Some(rect)
         }
     }
 }
@@ -174,45 +471,85 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut fb = Framebuffer::new(&args.device)?;
     let fb_info = get_framebuffer_info(&fb);
 
-    // Create framebuffer frame buffer
+    // Create framebuffer frame buffer and blank the whole screen once up front: afterwards only
+    // the rectangles each frame actually touches are flushed, so anything never painted over
+    // (letterboxing, a small off-center animation) would otherwise keep showing whatever was on
+    // screen before the player started.
     let mut fb_frame = vec![0; (fb.frame.len()) as usize];
+    fb.write_frame(&fb_frame);
     let mut frame_prepare_time = Instant::now();
 
-    // Decode GIF file
-    let mut decoder = get_gif_decoder(&args.gif_file)?;
-    let global_palette = decoder.global_palette().unwrap_or_default();
-    let global_palette = global_palette.to_vec();
+    // Open the animation file, selecting a decoder by its extension
+    let mut source = open_animation(&args.input_file)?;
+    let (source_width, source_height) = source.dimensions();
+    let scale = compute_scale(args.scale_mode, source_width, source_height, fb_info.width, fb_info.height);
 
     // Calulcate Offset
     let offset = if args.center {
-        Offset {
-            x: (fb_info.width - decoder.width() as isize) / 2,
-            y: (fb_info.height - decoder.height() as isize) / 2,
-        }
+        Offset { x: (fb_info.width - scale.target_width) / 2, y: (fb_info.height - scale.target_height) / 2 }
     } else {
         Offset { x: 0, y: 0 }
     };
 
+    // `--once` is shorthand for `--loop 1`; otherwise an explicit `--loop` override wins over
+    // the file's own embedded loop count.
+    let repeat = if args.once { Repeat::Finite(1) } else { args.loop_count.unwrap_or_else(|| source.repeat()) };
+    let mut iterations = 0u32;
+
     loop {
-        // Process each frame of the GIF file
-        while let Some(gif_frame) = decoder.read_next_frame()? {
-            let gif_palette = gif_frame.palette.as_ref().unwrap_or(&global_palette);
+        let mut pending_disposal: Option<PendingDisposal> = None;
+        let mut previous_snapshot: Option<Vec<u8>> = None;
+
+        // Process each frame of the animation file
+        while let Some(frame) = source.next_frame()? {
+            let frame = scale_frame(frame, &scale);
+            let rect = Rect { left: offset.x + frame.left, top: offset.y + frame.top, width: frame.width, height: frame.height };
+
+            // Apply the previous frame's disposal before painting this one
+            let mut dirty = pending_disposal
+                .take()
+                .and_then(|disposal| dispose_frame(disposal, &mut previous_snapshot, &mut fb_frame, &fb_info));
+
+            // A `Previous` disposal needs to restore what's underneath this frame, so snapshot
+            // it now, before this frame is painted over it
+            if frame.dispose == DisposalMethod::Previous {
+                if let Some(clipped) = rect.clip(&fb_info) {
+                    previous_snapshot = Some(snapshot_rect(&fb_frame, &fb_info, clipped));
+                }
+            }
+
+            dirty = union_rects(dirty, process_frame(&frame, &mut fb_frame, &fb_info, &offset));
+
+            if let Some(dirty) = dirty {
+                write_dirty_rect(&mut fb, &fb_frame, &fb_info, dirty);
+            }
 
-            process_gif_frame(gif_frame, gif_palette, &mut fb_frame, &fb_info, &offset);
-            fb.write_frame(&fb_frame);
+            pending_disposal = Some(PendingDisposal { method: frame.dispose, rect });
 
-            let delay = args.interval * gif_frame.delay as u64;
+            let delay = args.interval * frame.delay;
             postpone_next_frame(delay, &frame_prepare_time.elapsed());
             frame_prepare_time = Instant::now();
         }
 
-        // Stop after one the GIF file loop, if specified
-        if args.once {
-            break;
+        // Stop once the effective loop count has been reached
+        iterations += 1;
+        if let Repeat::Finite(n) = repeat {
+            if iterations >= n as u32 {
+                break;
+            }
+        }
+
+        // Apply the last frame's disposal before restarting; otherwise a `Background`/`Previous`
+        // disposal owed by the closing frame is silently dropped and ghosts into frame 0 of the
+        // next pass.
+        if let Some(disposal) = pending_disposal.take() {
+            if let Some(dirty) = dispose_frame(disposal, &mut previous_snapshot, &mut fb_frame, &fb_info) {
+                write_dirty_rect(&mut fb, &fb_frame, &fb_info, dirty);
+            }
         }
 
-        // Reinitialize the decoder to the beginning of the GIF file
-        decoder = get_gif_decoder(&args.gif_file)?;
+        // Restart the source from the beginning of the animation file
+        source.rewind()?;
     }
 
     // Set keyboard display mode back to text